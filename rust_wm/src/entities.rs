@@ -1,9 +1,11 @@
 use crate::ffi_helpers::*;
 use crate::input_inhibitor::{focus_exclusive_client, InputInhibitor};
+use crate::ipc;
 use mir_rs::*;
 use std::cmp;
 use std::collections::BTreeMap;
 use std::ptr;
+use std::sync::mpsc;
 
 pub type Id = u64;
 
@@ -33,6 +35,9 @@ pub struct Window {
   pub y: i32,
   pub size: Size,
   pub is_dragged: bool,
+  // Set by a matching window rule to force this window out of tiling,
+  // overriding the default is_tiled() heuristics below.
+  pub forced_float: bool,
 }
 
 impl Window {
@@ -52,6 +57,7 @@ impl Window {
         height: 0,
       },
       is_dragged: false,
+      forced_float: false,
     }
   }
 
@@ -134,7 +140,7 @@ impl Window {
   }
 
   pub fn is_tiled(&self) -> bool {
-    self.name() != "Ulauncher window title"
+    !self.forced_float
       && !self.has_parent()
       && (self.type_() == raw::MirWindowType::mir_window_type_normal
         || self.type_() == raw::MirWindowType::mir_window_type_freestyle)
@@ -147,13 +153,73 @@ impl Window {
   }
 }
 
+#[derive(Debug)]
+pub struct Column {
+  pub windows: Vec<Id>,
+  pub width: i32,
+  pub active: usize,
+}
+
+impl Column {
+  pub fn new(window: Id, width: i32) -> Column {
+    Column {
+      windows: vec![window],
+      width,
+      active: 0,
+    }
+  }
+
+  pub fn active_window(&self) -> Option<Id> {
+    self.windows.get(self.active).copied()
+  }
+
+  pub fn window_index(&self, window: Id) -> Option<usize> {
+    self
+      .windows
+      .iter()
+      .enumerate()
+      .find(|(_, w)| **w == window)
+      .map(|(index, _)| index)
+  }
+
+  // Splits `total_height` between the stacked windows, reserving
+  // `inner_gaps` between each pair and respecting each window's
+  // (min_height, max_height) from `bounds`, giving the remainder to the last
+  // window. `bounds` must have one entry per window, in `self.windows` order.
+  pub fn allocate_heights(&self, bounds: &[(i32, i32)], inner_gaps: i32, total_height: i32) -> Vec<i32> {
+    let count = self.windows.len() as i32;
+    let gap_total = inner_gaps * cmp::max(count - 1, 0);
+    let available = cmp::max(total_height - gap_total, 0);
+    let mut remaining = available;
+    let mut heights = Vec::with_capacity(self.windows.len());
+
+    for (i, (min_height, max_height)) in bounds.iter().enumerate() {
+      let share = if i as i32 == count - 1 {
+        remaining
+      } else {
+        remaining / (count - i as i32)
+      };
+      let share = cmp::max(cmp::min(share, *max_height), *min_height);
+      heights.push(share);
+      remaining -= share;
+    }
+
+    heights
+  }
+}
+
 #[derive(Debug)]
 pub struct Workspace {
   pub id: Id,
   pub on_monitor: Option<Id>,
   pub scroll_left: i32,
-  pub windows: Vec<Id>,
+  pub columns: Vec<Column>,
   pub active_window: Option<Id>,
+  // Set for workspaces pinned via Config's `named_workspaces`. Named
+  // workspaces are addressable by `WindowManager::workspace_by_name` and are
+  // never handed out as an unused workspace by
+  // `get_or_create_unused_workspace`.
+  pub name: Option<String>,
 }
 
 impl Workspace {
@@ -162,26 +228,43 @@ impl Workspace {
       id: id_generator.next_id(),
       on_monitor: None,
       scroll_left: 0,
-      windows: vec![],
+      columns: vec![],
       active_window: None,
+      name: None,
     }
   }
 
+  pub fn new_named(id_generator: &mut IdGenerator, name: String) -> Workspace {
+    Workspace {
+      name: Some(name),
+      ..Workspace::new(id_generator)
+    }
+  }
+
+  pub fn windows(&self) -> impl Iterator<Item = Id> + '_ {
+    self.columns.iter().flat_map(|column| column.windows.iter().copied())
+  }
+
   pub fn get_tiled_windows(&self, wm: &WindowManager) -> Vec<Id> {
     self
-      .windows
+      .windows()
+      .filter(|w| wm.get_window(*w).is_tiled())
+      .collect()
+  }
+
+  pub fn get_tiled_columns<'a>(&'a self, wm: &WindowManager) -> Vec<&'a Column> {
+    self
+      .columns
       .iter()
-      .filter(|w| wm.get_window(**w).is_tiled())
-      .copied()
+      .filter(|column| column.windows.iter().any(|w| wm.get_window(*w).is_tiled()))
       .collect()
   }
 
   pub fn get_window_index(&self, window: Id) -> Option<usize> {
     self
-      .windows
-      .iter()
+      .windows()
       .enumerate()
-      .find(|(_, w)| **w == window)
+      .find(|(_, w)| *w == window)
       .map(|(index, _)| index)
   }
 
@@ -194,10 +277,60 @@ impl Workspace {
       .map(|(index, _)| index)
   }
 
+  pub fn column_of_window(&self, window: Id) -> Option<usize> {
+    self
+      .columns
+      .iter()
+      .enumerate()
+      .find(|(_, column)| column.windows.contains(&window))
+      .map(|(index, _)| index)
+  }
+
+  // Inserts `window` as the sole member of a brand new column at `index`.
+  pub fn add_window_in_new_column(&mut self, window: Id, index: usize, width: i32) {
+    self.columns.insert(index, Column::new(window, width));
+  }
+
+  // Stacks `window` at the bottom of the column at `column_index` and makes
+  // it the column's active window.
+  pub fn add_window_to_column(&mut self, window: Id, column_index: usize) {
+    let column = &mut self.columns[column_index];
+    column.windows.push(window);
+    column.active = column.windows.len() - 1;
+  }
+
+  // Removes `window` from the workspace, dropping its column if it was the
+  // last window in it. Returns the column index the window used to live in.
+  pub fn remove_window(&mut self, window: Id) -> Option<usize> {
+    let column_index = self.column_of_window(window)?;
+    let column = &mut self.columns[column_index];
+    let window_index = column.window_index(window)?;
+    column.windows.remove(window_index);
+
+    if column.windows.is_empty() {
+      self.columns.remove(column_index);
+    } else if column.active >= column.windows.len() {
+      column.active = column.windows.len() - 1;
+    }
+
+    Some(column_index)
+  }
+
   pub fn swap_windows(&mut self, a: Id, b: Id) {
-    let a_raw_index = self.get_window_index(a).unwrap();
-    let b_raw_index = self.get_window_index(b).unwrap();
-    self.windows.swap(a_raw_index, b_raw_index);
+    let a_column = self.column_of_window(a).unwrap();
+    let b_column = self.column_of_window(b).unwrap();
+
+    if a_column == b_column {
+      let column = &mut self.columns[a_column];
+      let a_index = column.window_index(a).unwrap();
+      let b_index = column.window_index(b).unwrap();
+      column.windows.swap(a_index, b_index);
+    } else {
+      let a_index = self.columns[a_column].window_index(a).unwrap();
+      let b_index = self.columns[b_column].window_index(b).unwrap();
+      self.columns[a_column].windows[a_index] = b;
+      self.columns[b_column].windows[b_index] = a;
+    }
   }
 }
 
@@ -234,6 +367,10 @@ pub struct MoveGesture {
   pub buttons: raw::MirPointerButtons,
   pub modifiers: input_event_modifier::Type,
   pub top_left: Point,
+  // Column index the dragged window would land in if dropped right now, kept
+  // up to date while the drag is in progress so it can be rendered as an
+  // insert hint and applied on release.
+  pub insert_hint: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -243,6 +380,82 @@ pub enum Gesture {
   None,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ColumnDirection {
+  Left,
+  Right,
+}
+
+// Criteria a window is matched against. At least one field must be set. Only
+// `name` can currently be checked against a live `Window` (there is no
+// app_id accessor on top of the Mir FFI types this crate wraps), so rules
+// that set only `app_id` are rejected at config load time.
+#[derive(Default, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct WindowMatch {
+  pub name: Option<String>,
+  pub app_id: Option<String>,
+}
+
+impl WindowMatch {
+  pub fn is_empty(&self) -> bool {
+    self.name.is_none() && self.app_id.is_none()
+  }
+
+  // Whether this criteria can ever match a window, given what we're able to
+  // check. `app_id`-only rules are accepted as YAML but can never match.
+  pub fn is_checkable(&self) -> bool {
+    self.name.is_some()
+  }
+}
+
+// What used to be a hardcoded `name != "Ulauncher window title"` check in
+// `Window::is_tiled` is now just a `Float` rule applied like any other; add
+// it to `window_rules` in config.yaml to restore that behavior:
+//   window_rules:
+//     - match: { name: "Ulauncher window title" }
+//       action: float
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowAction {
+  Float,
+  Fullscreen,
+  Workspace(String),
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct WindowRule {
+  #[serde(rename = "match")]
+  pub match_: WindowMatch,
+  pub action: WindowAction,
+}
+
+impl WindowRule {
+  pub fn matches(&self, name: &str) -> bool {
+    match &self.match_.name {
+      Some(match_name) => match_name == name,
+      None => false,
+    }
+  }
+}
+
+// Index of a window's column after it has been removed from `source_column`.
+// Removing the window only collapses `source_column` away if it was the
+// column's sole occupant, which shifts every later column index down by one;
+// otherwise the column survives and indices are unaffected.
+fn shifted_target_index(target_index: usize, source_column: usize, source_had_one_window: bool) -> usize {
+  if target_index > source_column && source_had_one_window {
+    target_index - 1
+  } else {
+    target_index
+  }
+}
+
+// NOTE: whoever constructs `WindowManager` (the compositor bootstrap, outside
+// this crate) must initialize every field below, including `scratchpad`,
+// `scratchpad_geometry`, `visible_scratchpad`, `inner_gaps`, `outer_gaps` and
+// `window_rules` added alongside columns/scratchpad/gaps support.
+// `ipc_commands` should be initialized to `None` and populated via
+// `start_ipc` once the window manager is otherwise ready.
 #[derive(Debug)]
 pub struct WindowManager {
   pub tools: *mut miral::WindowManagerTools,
@@ -259,6 +472,25 @@ pub struct WindowManager {
   pub gesture: Gesture,
   pub active_window: Option<Id>,
   pub active_workspace: Id,
+
+  // Windows hidden off-strip, most-recently-stashed last. `scratchpad_geometry`
+  // remembers where to put a window back when it's summoned.
+  pub scratchpad: Vec<Id>,
+  pub scratchpad_geometry: BTreeMap<Id, (Point, Size)>,
+  pub visible_scratchpad: Option<Id>,
+
+  // Spacing, in pixels, between tiled windows and between the tiled strip
+  // and the monitor edge. Mirrors Config's `inner_gaps`/`outer_gaps`.
+  pub inner_gaps: i32,
+  pub outer_gaps: i32,
+
+  // Pending requests from the control socket, drained by `poll_ipc` on each
+  // tick of the main loop. `None` until `start_ipc` has been called.
+  pub ipc_commands: Option<mpsc::Receiver<ipc::IpcCommand>>,
+
+  // Rules from Config's `window_rules`, applied to every window as it is
+  // added. Checked in order; the first match wins.
+  pub window_rules: Vec<WindowRule>,
 }
 
 impl WindowManager {
@@ -297,6 +529,178 @@ impl WindowManager {
       .find(|w| w.window_info as *const _ == window_info)
   }
 
+  // Finds the window whose rendered rectangle contains `point`, if any. The
+  // rectangle is grown by half of `inner_gaps` on each side so that the gap
+  // between two windows maps to whichever of them is nearer, rather than to
+  // neither.
+  pub fn window_under(&self, point: Point) -> Option<Id> {
+    let half_gap = self.inner_gaps / 2;
+    self
+      .windows
+      .values()
+      .find(|w| {
+        let top_left = w.rendered_top_left();
+        let size = w.rendered_size();
+        point.x >= top_left.x - half_gap
+          && point.x < top_left.x + size.width + half_gap
+          && point.y >= top_left.y - half_gap
+          && point.y < top_left.y + size.height + half_gap
+      })
+      .map(|w| w.id)
+  }
+
+  // The region of `monitor` available for tiled windows once `outer_gaps`
+  // insets it from the screen edge.
+  pub fn usable_monitor_area(&self, monitor: &Monitor) -> (Point, Size) {
+    let top_left = Point {
+      x: monitor.extents.top_left.x + self.outer_gaps,
+      y: monitor.extents.top_left.y + self.outer_gaps,
+    };
+    let size = Size {
+      width: cmp::max(monitor.extents.size.width - self.outer_gaps * 2, 0),
+      height: cmp::max(monitor.extents.size.height - self.outer_gaps * 2, 0),
+    };
+    (top_left, size)
+  }
+
+  // Recomputes on-screen position and size for every tiled column/window on
+  // `workspace_id`, stacking each column's windows vertically via
+  // `Column::allocate_heights`, insetting the strip by `outer_gaps` and
+  // spacing columns apart by `inner_gaps`. No-op if the workspace isn't
+  // shown on a monitor (e.g. while it's still in the unused pool).
+  pub fn layout_workspace(&mut self, workspace_id: Id) -> () {
+    let monitor = match self.monitor_by_workspace(workspace_id) {
+      Some(monitor) => monitor,
+      None => return,
+    };
+    let (area_top_left, area_size) = self.usable_monitor_area(monitor);
+
+    let workspace = self.get_workspace(workspace_id);
+    let mut x = area_top_left.x - workspace.scroll_left;
+    let mut placements = vec![];
+
+    for column in workspace.get_tiled_columns(self) {
+      let bounds: Vec<(i32, i32)> = column
+        .windows
+        .iter()
+        .map(|window_id| {
+          let window = self.get_window(*window_id);
+          (window.min_height(), window.max_height())
+        })
+        .collect();
+      let heights = column.allocate_heights(&bounds, self.inner_gaps, area_size.height);
+      let mut y = area_top_left.y;
+      for (window_id, height) in column.windows.iter().zip(heights.iter()) {
+        placements.push((*window_id, x, y, column.width, *height));
+        y += height + self.inner_gaps;
+      }
+      x += column.width + self.inner_gaps;
+    }
+
+    for (window_id, x, y, width, height) in placements {
+      if let Some(window) = self.windows.get_mut(&window_id) {
+        if !window.is_tiled() {
+          continue;
+        }
+        window.move_to(x, y);
+        window.resize(Size { width, height });
+      }
+    }
+  }
+
+  // Binds the control socket and remembers its receiver so `poll_ipc` can
+  // drain it. Called once during compositor startup, after which control
+  // requests (see `crate::ipc::Request`) can be sent to the socket.
+  pub fn start_ipc(&mut self) -> std::io::Result<()> {
+    self.ipc_commands = Some(ipc::start()?);
+    Ok(())
+  }
+
+  // Applies every control-socket request queued since the last call. A
+  // no-op until `start_ipc` has been called. Intended to be called once per
+  // tick of the main loop.
+  pub fn poll_ipc(&mut self) -> () {
+    let commands = match &self.ipc_commands {
+      Some(commands) => commands,
+      None => return,
+    };
+
+    let pending: Vec<_> = commands.try_iter().collect();
+    for (request, response_tx) in pending {
+      let response = ipc::handle_request(self, request);
+      let _ = response_tx.send(response);
+    }
+  }
+
+  // Recomputes the insert hint for the in-progress move gesture, given the
+  // current cursor position. A no-op unless a tiled window is being dragged.
+  // Called by the pointer-motion handler (outside this crate slice) on every
+  // motion event while `self.gesture` is `Gesture::Move`.
+  pub fn update_move_insert_hint(&mut self, cursor: Point) -> () {
+    let window_id = match &self.gesture {
+      Gesture::Move(move_gesture) => move_gesture.window,
+      _ => return,
+    };
+    if !self.get_window(window_id).is_tiled() {
+      return;
+    }
+
+    let workspace = self.get_workspace(self.get_window(window_id).workspace);
+    let hovered = self
+      .window_under(cursor)
+      .filter(|id| *id != window_id)
+      .filter(|id| self.get_window(*id).is_tiled());
+
+    let hint = hovered.and_then(|hovered_id| {
+      let hovered_column = workspace.column_of_window(hovered_id)?;
+      let column_window = self.get_window(hovered_id);
+      let midpoint = column_window.rendered_top_left().x + column_window.rendered_size().width / 2;
+      Some(if cursor.x < midpoint {
+        hovered_column
+      } else {
+        hovered_column + 1
+      })
+    });
+
+    if let Gesture::Move(move_gesture) = &mut self.gesture {
+      move_gesture.insert_hint = hint;
+    }
+  }
+
+  // Applies the pending insert hint on drag release, splicing the dragged
+  // window into its new column position. No-ops if there is no hint, or the
+  // window stopped being tiled mid-drag. Called by the pointer-button-release
+  // handler (outside this crate slice) when releasing ends a move gesture.
+  pub fn apply_move_insert_hint(&mut self) -> () {
+    let (window_id, hint) = match &self.gesture {
+      Gesture::Move(move_gesture) => (move_gesture.window, move_gesture.insert_hint),
+      _ => return,
+    };
+
+    let hint = match hint {
+      Some(hint) => hint,
+      None => return,
+    };
+    if !self.get_window(window_id).is_tiled() {
+      return;
+    }
+
+    let workspace_id = self.get_window(window_id).workspace;
+    let width = self.get_window(window_id).size.width;
+    let workspace = self.workspaces.get_mut(&workspace_id).unwrap();
+
+    let source_column = match workspace.column_of_window(window_id) {
+      Some(index) => index,
+      None => return,
+    };
+
+    let source_had_one_window = workspace.columns[source_column].windows.len() == 1;
+    workspace.remove_window(window_id);
+    let target_index = shifted_target_index(hint, source_column, source_had_one_window);
+    workspace.add_window_in_new_column(window_id, target_index, width);
+    self.layout_workspace(workspace_id);
+  }
+
   pub fn active_window(&self) -> Option<&Window> {
     self.active_window.and_then(|id| self.windows.get(&id))
   }
@@ -308,11 +712,118 @@ impl WindowManager {
       .expect("Active workspace not found")
   }
 
+  pub fn workspace_by_name(&self, name: &str) -> Option<Id> {
+    self
+      .workspaces
+      .values()
+      .find(|w| w.name.as_deref() == Some(name))
+      .map(|w| w.id)
+  }
+
+  // Returns the id of the named workspace, creating and pinning it if it
+  // doesn't exist yet. Called for each entry of Config's `named_workspaces`
+  // at startup.
+  pub fn ensure_named_workspace(&mut self, name: &str) -> Id {
+    if let Some(id) = self.workspace_by_name(name) {
+      return id;
+    }
+
+    let workspace = Workspace::new_named(&mut self.workspace_id_generator, name.to_string());
+    let id = workspace.id;
+    self.workspaces.insert(id, workspace);
+    id
+  }
+
+  // Parks every window on `workspace_id` off-screen, the same way
+  // `send_to_scratchpad` hides a stashed window, so it doesn't linger
+  // visible once its workspace stops being shown on a monitor. Also hides
+  // the scratchpad overlay if it's currently summoned onto this workspace,
+  // since a summoned window lives outside `columns` and isn't covered above.
+  fn hide_workspace_windows(&mut self, workspace_id: Id) -> () {
+    let windows: Vec<Id> = self.get_workspace(workspace_id).windows().collect();
+    for window_id in windows {
+      let window = self.windows.get_mut(&window_id).unwrap();
+      let size = window.rendered_size();
+      window.move_to(-size.width - 100, -size.height - 100);
+    }
+
+    if let Some(window_id) = self.visible_scratchpad {
+      if self.get_window(window_id).workspace == workspace_id {
+        let window = self.windows.get_mut(&window_id).unwrap();
+        let size = window.rendered_size();
+        window.move_to(-size.width - 100, -size.height - 100);
+
+        if self.active_window == Some(window_id) {
+          self.active_window = None;
+        }
+        self.visible_scratchpad = None;
+      }
+    }
+  }
+
+  // Makes the named workspace active, moving it onto the active monitor if
+  // it isn't already shown on one.
+  pub fn focus_workspace_by_name(&mut self, name: &str) -> () {
+    let workspace_id = match self.workspace_by_name(name) {
+      Some(id) => id,
+      None => return,
+    };
+
+    if self.get_workspace(workspace_id).on_monitor.is_none() {
+      let monitor_id = match self.monitor_by_workspace(self.active_workspace) {
+        Some(monitor) => monitor.id,
+        None => return,
+      };
+
+      let previous_workspace_id = self.monitors.get(&monitor_id).unwrap().workspace;
+      self.hide_workspace_windows(previous_workspace_id);
+      self
+        .workspaces
+        .get_mut(&previous_workspace_id)
+        .unwrap()
+        .on_monitor = None;
+
+      self.monitors.get_mut(&monitor_id).unwrap().workspace = workspace_id;
+      self.workspaces.get_mut(&workspace_id).unwrap().on_monitor = Some(monitor_id);
+      self.layout_workspace(workspace_id);
+    }
+
+    self.active_workspace = workspace_id;
+    let active_window = self.get_workspace(workspace_id).active_window;
+    self.focus_window(active_window);
+  }
+
+  // Pre-creates and pins every workspace in `names`, so they exist (and are
+  // never handed out by `get_or_create_unused_workspace` as unused)
+  // before anything tries to focus or move a window to them. Called once
+  // during compositor startup with Config's `named_workspaces`.
+  pub fn init_named_workspaces(&mut self, names: &[String]) -> () {
+    for name in names {
+      self.ensure_named_workspace(name);
+    }
+  }
+
+  // Moves `window_id` onto the named workspace, creating it if needed.
+  pub fn move_window_to_named_workspace(&mut self, window_id: Id, name: &str) -> () {
+    let target_workspace_id = self.ensure_named_workspace(name);
+    let width = self.get_window(window_id).size.width;
+
+    if self.remove_window_from_workspace(window_id).is_err() {
+      return;
+    }
+
+    let workspace = self.workspaces.get_mut(&target_workspace_id).unwrap();
+    let index = workspace.columns.len();
+    workspace.add_window_in_new_column(window_id, index, width);
+    self.windows.get_mut(&window_id).unwrap().workspace = target_workspace_id;
+    self.layout_workspace(target_workspace_id);
+  }
+
   pub fn get_or_create_unused_workspace(&mut self) -> Id {
     let unused_workspaces = self
       .workspaces
       .values()
-      .filter(|w| w.on_monitor == None)
+      .filter(|w| w.on_monitor == None && w.name.is_none())
       .collect::<Vec<_>>();
 
     match unused_workspaces.first() {
@@ -345,19 +856,24 @@ impl WindowManager {
 
   pub fn add_window(&mut self, window: Window) -> () {
     println!("WM: {:?}, adding: {:?}", &self, &window);
-    let workspace = self.workspaces.get_mut(&window.workspace).unwrap();
+    let workspace_id = window.workspace;
+    let workspace = self.workspaces.get_mut(&workspace_id).unwrap();
 
-    if let Some(active_window) = self.active_window {
-      let index = workspace
-        .get_window_index(active_window)
-        .expect("add window workspace");
-      workspace.windows.insert(index + 1, window.id);
-    } else {
-      workspace.windows.push(window.id);
-    }
+    // `active_window` may be a scratchpad window summoned via
+    // `toggle_scratchpad`, which is never re-inserted into any column, so
+    // fall back to appending rather than assuming it has one.
+    let index = self
+      .active_window
+      .and_then(|active_window| workspace.column_of_window(active_window))
+      .map(|index| index + 1)
+      .unwrap_or(workspace.columns.len());
+    workspace.add_window_in_new_column(window.id, index, window.size.width);
 
     let window_id = window.id;
+    let window_name = window.name();
     self.windows.insert(window.id, window);
+    self.apply_matching_window_rule(window_id, &window_name);
+    self.layout_workspace(workspace_id);
 
     let window = self.get_window(window_id);
     if !window.has_parent() {
@@ -369,12 +885,65 @@ impl WindowManager {
     }
   }
 
+  // Applies the first rule in `window_rules` matching `name`, if any:
+  // `Float`/`Fullscreen` force the window out of tiling (fullscreen windows
+  // additionally take over the usable monitor area), `Workspace` moves it to
+  // the named workspace.
+  fn apply_matching_window_rule(&mut self, window_id: Id, name: &str) -> () {
+    let action = match self.window_rules.iter().find(|rule| rule.matches(name)) {
+      Some(rule) => match &rule.action {
+        WindowAction::Float => WindowAction::Float,
+        WindowAction::Fullscreen => WindowAction::Fullscreen,
+        WindowAction::Workspace(target) => WindowAction::Workspace(target.clone()),
+      },
+      None => return,
+    };
+
+    match action {
+      WindowAction::Float => {
+        self.windows.get_mut(&window_id).unwrap().forced_float = true;
+        let _ = self.remove_window_from_workspace(window_id);
+      }
+      WindowAction::Fullscreen => {
+        self.windows.get_mut(&window_id).unwrap().forced_float = true;
+        let _ = self.remove_window_from_workspace(window_id);
+        if let Some(monitor) = self.monitor_by_window(window_id) {
+          let (top_left, size) = self.usable_monitor_area(monitor);
+          let window = self.windows.get_mut(&window_id).unwrap();
+          window.move_to(top_left.x, top_left.y);
+          window.resize(size);
+        }
+      }
+      WindowAction::Workspace(name) => {
+        self.move_window_to_named_workspace(window_id, &name);
+      }
+    }
+  }
+
   pub fn delete_window(&mut self, window_id: Id) -> () {
     self.input_inhibitor.clear_if_dead();
 
-    self
-      .remove_window_from_workspace(window_id)
-      .expect("nowindow in workspace advise_delete_window");
+    if let Gesture::Move(move_gesture) = &self.gesture {
+      if move_gesture.window == window_id {
+        self.gesture = Gesture::None;
+      }
+    }
+
+    if self.scratchpad.contains(&window_id) {
+      self.scratchpad.retain(|id| *id != window_id);
+      self.scratchpad_geometry.remove(&window_id);
+      if self.visible_scratchpad == Some(window_id) {
+        self.visible_scratchpad = None;
+      }
+    } else {
+      // Windows floated or fullscreened by a window rule were taken out of
+      // their column when added, so they have no column to be removed from.
+      let forced_float = self.get_window(window_id).forced_float;
+      let removed = self.remove_window_from_workspace(window_id);
+      if !forced_float {
+        removed.expect("nowindow in workspace advise_delete_window");
+      }
+    }
     self.windows.remove(&window_id);
 
     if self.active_window == Some(window_id) {
@@ -415,11 +984,64 @@ impl WindowManager {
       }
     }
     let workspace = self.workspaces.get_mut(&workspace_id).unwrap();
-    let raw_index = workspace.get_window_index(window).ok_or(())?;
-    workspace.windows.remove(raw_index);
+    workspace.remove_window(window).ok_or(())?;
+    self.layout_workspace(workspace_id);
     Ok(())
   }
 
+  // Moves `window` out of its column and into the adjacent column in
+  // `direction`, stacking it at the bottom. Does nothing if there is no such
+  // neighbour to consume into.
+  pub fn consume_into_adjacent_column(&mut self, window_id: Id, direction: ColumnDirection) -> () {
+    let workspace_id = self.get_window(window_id).workspace;
+    let workspace = self.workspaces.get_mut(&workspace_id).unwrap();
+
+    let column_index = match workspace.column_of_window(window_id) {
+      Some(index) => index,
+      None => return,
+    };
+
+    let target_index = match direction {
+      ColumnDirection::Left => {
+        if column_index == 0 {
+          return;
+        }
+        column_index - 1
+      }
+      ColumnDirection::Right => column_index + 1,
+    };
+
+    if target_index >= workspace.columns.len() || target_index == column_index {
+      return;
+    }
+
+    let source_had_one_window = workspace.columns[column_index].windows.len() == 1;
+    workspace.remove_window(window_id);
+    let target_index = shifted_target_index(target_index, column_index, source_had_one_window);
+    workspace.add_window_to_column(window_id, target_index);
+    self.layout_workspace(workspace_id);
+  }
+
+  // Ejects `window` out of whatever column it is stacked in and gives it its
+  // own new column immediately to the right.
+  pub fn eject_from_column(&mut self, window_id: Id) -> () {
+    let workspace_id = self.get_window(window_id).workspace;
+    let width = self.get_window(window_id).size.width;
+    let workspace = self.workspaces.get_mut(&workspace_id).unwrap();
+
+    let column_index = match workspace.column_of_window(window_id) {
+      Some(index) => index,
+      None => return,
+    };
+    if workspace.columns[column_index].windows.len() <= 1 {
+      return;
+    }
+
+    workspace.remove_window(window_id);
+    workspace.add_window_in_new_column(window_id, column_index + 1, width);
+    self.layout_workspace(workspace_id);
+  }
+
   pub fn focus_window(&mut self, window_id: Option<Id>) -> () {
     self.active_window = window_id;
     if let Some(window_id) = window_id {
@@ -439,4 +1061,262 @@ impl WindowManager {
       }
     }
   }
+
+  // Removes `window_id` from its workspace and parks it off-screen, most
+  // recently stashed so it's the next one `toggle_scratchpad` summons.
+  pub fn send_to_scratchpad(&mut self, window_id: Id) -> () {
+    let window = self.get_window(window_id);
+    self
+      .scratchpad_geometry
+      .insert(window_id, (window.rendered_top_left(), window.rendered_size()));
+
+    self
+      .remove_window_from_workspace(window_id)
+      .expect("nowindow in workspace send_to_scratchpad");
+    if self.active_window == Some(window_id) {
+      self.active_window = None;
+    }
+
+    let window = self.windows.get_mut(&window_id).unwrap();
+    let size = window.rendered_size();
+    window.move_to(-size.width - 100, -size.height - 100);
+
+    self.scratchpad.retain(|id| *id != window_id);
+    self.scratchpad.push(window_id);
+    if self.visible_scratchpad == Some(window_id) {
+      self.visible_scratchpad = None;
+    }
+  }
+
+  // Summons the most-recently-stashed scratchpad window as a floating
+  // overlay on the active monitor, or hides it again if it's already shown.
+  pub fn toggle_scratchpad(&mut self) -> () {
+    if let Some(window_id) = self.visible_scratchpad {
+      let window = self.windows.get_mut(&window_id).unwrap();
+      let size = window.rendered_size();
+      window.move_to(-size.width - 100, -size.height - 100);
+
+      if self.active_window == Some(window_id) {
+        self.active_window = None;
+      }
+      self.visible_scratchpad = None;
+      return;
+    }
+
+    let window_id = match self.scratchpad.last().copied() {
+      Some(window_id) => window_id,
+      None => return,
+    };
+
+    let (monitor_top_left, monitor_size) = match self.monitor_by_workspace(self.active_workspace) {
+      Some(monitor) => (monitor.extents.top_left, monitor.extents.size),
+      None => return,
+    };
+    let size = self
+      .scratchpad_geometry
+      .get(&window_id)
+      .map(|(_, size)| *size)
+      .unwrap_or_else(|| self.get_window(window_id).rendered_size());
+    let top_left = Point {
+      x: monitor_top_left.x + (monitor_size.width - size.width) / 2,
+      y: monitor_top_left.y + (monitor_size.height - size.height) / 2,
+    };
+
+    let active_workspace = self.active_workspace;
+    let window = self.windows.get_mut(&window_id).unwrap();
+    window.workspace = active_workspace;
+    window.forced_float = true;
+    window.move_to(top_left.x, top_left.y);
+    window.resize(size);
+
+    self.visible_scratchpad = Some(window_id);
+    self.activate_window(window_id);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn shifted_target_index_unaffected_before_source() {
+    assert_eq!(shifted_target_index(0, 2, true), 0);
+    assert_eq!(shifted_target_index(2, 2, true), 2);
+  }
+
+  #[test]
+  fn shifted_target_index_shifts_past_collapsed_source() {
+    assert_eq!(shifted_target_index(3, 1, true), 2);
+  }
+
+  #[test]
+  fn shifted_target_index_unaffected_when_source_survives() {
+    assert_eq!(shifted_target_index(3, 1, false), 3);
+  }
+
+  fn new_workspace() -> Workspace {
+    let mut ids = IdGenerator::new();
+    Workspace::new(&mut ids)
+  }
+
+  #[test]
+  fn add_window_in_new_column_inserts_at_index() {
+    let mut workspace = new_workspace();
+    workspace.add_window_in_new_column(1, 0, 100);
+    workspace.add_window_in_new_column(2, 1, 100);
+    workspace.add_window_in_new_column(3, 1, 100);
+
+    assert_eq!(workspace.windows().collect::<Vec<_>>(), vec![1, 3, 2]);
+  }
+
+  #[test]
+  fn add_window_to_column_stacks_and_activates() {
+    let mut workspace = new_workspace();
+    workspace.add_window_in_new_column(1, 0, 100);
+    workspace.add_window_to_column(2, 0);
+
+    assert_eq!(workspace.columns[0].windows, vec![1, 2]);
+    assert_eq!(workspace.columns[0].active, 1);
+  }
+
+  #[test]
+  fn column_of_window_finds_containing_column() {
+    let mut workspace = new_workspace();
+    workspace.add_window_in_new_column(1, 0, 100);
+    workspace.add_window_in_new_column(2, 1, 100);
+    workspace.add_window_to_column(3, 1);
+
+    assert_eq!(workspace.column_of_window(1), Some(0));
+    assert_eq!(workspace.column_of_window(3), Some(1));
+    assert_eq!(workspace.column_of_window(99), None);
+  }
+
+  #[test]
+  fn remove_window_collapses_empty_column() {
+    let mut workspace = new_workspace();
+    workspace.add_window_in_new_column(1, 0, 100);
+    workspace.add_window_in_new_column(2, 1, 100);
+
+    let column_index = workspace.remove_window(1);
+
+    assert_eq!(column_index, Some(0));
+    assert_eq!(workspace.columns.len(), 1);
+    assert_eq!(workspace.column_of_window(2), Some(0));
+  }
+
+  #[test]
+  fn remove_window_keeps_column_with_remaining_windows() {
+    let mut workspace = new_workspace();
+    workspace.add_window_in_new_column(1, 0, 100);
+    workspace.add_window_to_column(2, 0);
+
+    let column_index = workspace.remove_window(2);
+
+    assert_eq!(column_index, Some(0));
+    assert_eq!(workspace.columns.len(), 1);
+    assert_eq!(workspace.columns[0].windows, vec![1]);
+  }
+
+  #[test]
+  fn swap_windows_within_same_column() {
+    let mut workspace = new_workspace();
+    workspace.add_window_in_new_column(1, 0, 100);
+    workspace.add_window_to_column(2, 0);
+
+    workspace.swap_windows(1, 2);
+
+    assert_eq!(workspace.columns[0].windows, vec![2, 1]);
+  }
+
+  #[test]
+  fn allocate_heights_splits_evenly_with_no_gaps() {
+    let column = Column::new(1, 100);
+    // `Column::new` only has one window; extend it to two so it matches the
+    // two-entry `bounds` we're allocating for.
+    let column = Column {
+      windows: vec![1, 2],
+      ..column
+    };
+    let bounds = [(0, i32::MAX), (0, i32::MAX)];
+
+    let heights = column.allocate_heights(&bounds, 0, 200);
+
+    assert_eq!(heights, vec![100, 100]);
+  }
+
+  #[test]
+  fn allocate_heights_reserves_inner_gaps() {
+    let column = Column {
+      windows: vec![1, 2],
+      ..Column::new(1, 100)
+    };
+    let bounds = [(0, i32::MAX), (0, i32::MAX)];
+
+    let heights = column.allocate_heights(&bounds, 10, 210);
+
+    assert_eq!(heights, vec![100, 100]);
+  }
+
+  #[test]
+  fn allocate_heights_clamps_to_min_and_max() {
+    let column = Column {
+      windows: vec![1, 2],
+      ..Column::new(1, 100)
+    };
+    let bounds = [(150, i32::MAX), (0, i32::MAX)];
+
+    let heights = column.allocate_heights(&bounds, 0, 200);
+
+    assert_eq!(heights[0], 150);
+  }
+
+  #[test]
+  fn window_match_is_empty_requires_a_field() {
+    assert!(WindowMatch::default().is_empty());
+    assert!(!WindowMatch {
+      name: Some("foo".to_string()),
+      app_id: None,
+    }
+    .is_empty());
+  }
+
+  #[test]
+  fn window_match_is_checkable_requires_name() {
+    assert!(!WindowMatch {
+      name: None,
+      app_id: Some("foo".to_string()),
+    }
+    .is_checkable());
+    assert!(WindowMatch {
+      name: Some("foo".to_string()),
+      app_id: None,
+    }
+    .is_checkable());
+  }
+
+  #[test]
+  fn window_rule_matches_by_name_only() {
+    let rule = WindowRule {
+      match_: WindowMatch {
+        name: Some("Ulauncher window title".to_string()),
+        app_id: None,
+      },
+      action: WindowAction::Float,
+    };
+
+    assert!(rule.matches("Ulauncher window title"));
+    assert!(!rule.matches("Firefox"));
+  }
+
+  #[test]
+  fn swap_windows_across_columns() {
+    let mut workspace = new_workspace();
+    workspace.add_window_in_new_column(1, 0, 100);
+    workspace.add_window_in_new_column(2, 1, 100);
+
+    workspace.swap_windows(1, 2);
+
+    assert_eq!(workspace.columns[0].windows, vec![2]);
+    assert_eq!(workspace.columns[1].windows, vec![1]);
+  }
 }
@@ -0,0 +1,227 @@
+use crate::entities::{ColumnDirection, Id, WindowManager};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::{env, fs};
+
+const SOCKET_ENV_VAR: &str = "CASCADE_IPC_SOCKET";
+const DEFAULT_SOCKET_PATH: &str = "~/.cache/cascade/cascade.sock";
+
+// Requests accepted on the control socket, one per line as JSON.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request {
+  ListWindows,
+  ListWorkspaces,
+  FocusWindow { id: Id },
+  MoveWindowToWorkspace { window: Id, workspace: Id },
+  FocusWorkspaceByName { name: String },
+  MoveWindowToNamedWorkspace { window: Id, name: String },
+  Scroll { workspace: Id, dx: i32 },
+  CloseWindow { id: Id },
+  ConsumeIntoColumn { window: Id, direction: ColumnDirection },
+  EjectFromColumn { window: Id },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WindowView {
+  pub id: Id,
+  pub name: String,
+  pub workspace: Id,
+  pub top_left: (i32, i32),
+  pub size: (i32, i32),
+  pub is_tiled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceView {
+  pub id: Id,
+  pub name: Option<String>,
+  pub on_monitor: Option<Id>,
+  pub scroll_left: i32,
+  pub windows: Vec<Id>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+  Windows(Vec<WindowView>),
+  Workspaces(Vec<WorkspaceView>),
+  Ok,
+  Error(String),
+}
+
+// A request paired with the channel its response should go back down.
+pub type IpcCommand = (Request, Sender<Response>);
+
+// Applies `request` to the live `wm`, run from the main loop so all
+// mutations stay on the thread that owns `WindowManagerTools`.
+pub fn handle_request(wm: &mut WindowManager, request: Request) -> Response {
+  match request {
+    Request::ListWindows => Response::Windows(
+      wm.windows
+        .values()
+        .map(|window| WindowView {
+          id: window.id,
+          name: window.name(),
+          workspace: window.workspace,
+          top_left: (window.rendered_top_left().x, window.rendered_top_left().y),
+          size: (window.rendered_size().width, window.rendered_size().height),
+          is_tiled: window.is_tiled(),
+        })
+        .collect(),
+    ),
+    Request::ListWorkspaces => Response::Workspaces(
+      wm.workspaces
+        .values()
+        .map(|workspace| WorkspaceView {
+          id: workspace.id,
+          name: workspace.name.clone(),
+          on_monitor: workspace.on_monitor,
+          scroll_left: workspace.scroll_left,
+          windows: workspace.windows().collect(),
+        })
+        .collect(),
+    ),
+    Request::FocusWindow { id } => {
+      if !wm.windows.contains_key(&id) {
+        return Response::Error(format!("No window with id {}", id));
+      }
+      wm.focus_window(Some(id));
+      Response::Ok
+    }
+    Request::MoveWindowToWorkspace { window, workspace } => {
+      if !wm.windows.contains_key(&window) {
+        return Response::Error(format!("No window with id {}", window));
+      }
+      if !wm.workspaces.contains_key(&workspace) {
+        return Response::Error(format!("No workspace with id {}", workspace));
+      }
+      let width = wm.get_window(window).size.width;
+      match wm.remove_window_from_workspace(window) {
+        Ok(()) => {
+          let target = wm.workspaces.get_mut(&workspace).unwrap();
+          let index = target.columns.len();
+          target.add_window_in_new_column(window, index, width);
+          wm.windows.get_mut(&window).unwrap().workspace = workspace;
+          wm.layout_workspace(workspace);
+          Response::Ok
+        }
+        Err(()) => Response::Error(format!("Window {} was not on a workspace", window)),
+      }
+    }
+    Request::FocusWorkspaceByName { name } => match wm.workspace_by_name(&name) {
+      Some(_) => {
+        wm.focus_workspace_by_name(&name);
+        Response::Ok
+      }
+      None => Response::Error(format!("No workspace named {:?}", name)),
+    },
+    Request::MoveWindowToNamedWorkspace { window, name } => {
+      if !wm.windows.contains_key(&window) {
+        return Response::Error(format!("No window with id {}", window));
+      }
+      wm.move_window_to_named_workspace(window, &name);
+      Response::Ok
+    }
+    Request::Scroll { workspace, dx } => {
+      if !wm.workspaces.contains_key(&workspace) {
+        return Response::Error(format!("No workspace with id {}", workspace));
+      }
+      wm.workspaces.get_mut(&workspace).unwrap().scroll_left += dx;
+      wm.layout_workspace(workspace);
+      Response::Ok
+    }
+    Request::CloseWindow { id } => match wm.windows.get(&id) {
+      Some(window) => {
+        window.ask_client_to_close(wm);
+        Response::Ok
+      }
+      None => Response::Error(format!("No window with id {}", id)),
+    },
+    Request::ConsumeIntoColumn { window, direction } => {
+      if !wm.windows.contains_key(&window) {
+        return Response::Error(format!("No window with id {}", window));
+      }
+      wm.consume_into_adjacent_column(window, direction);
+      Response::Ok
+    }
+    Request::EjectFromColumn { window } => {
+      if !wm.windows.contains_key(&window) {
+        return Response::Error(format!("No window with id {}", window));
+      }
+      wm.eject_from_column(window);
+      Response::Ok
+    }
+  }
+}
+
+fn socket_path() -> String {
+  let path = env::var(SOCKET_ENV_VAR).unwrap_or_else(|_| DEFAULT_SOCKET_PATH.to_string());
+  shellexpand::tilde(&path).to_string()
+}
+
+fn handle_connection(stream: UnixStream, command_tx: Sender<IpcCommand>) {
+  let mut writer = match stream.try_clone() {
+    Ok(stream) => stream,
+    Err(_) => return,
+  };
+  let reader = BufReader::new(stream);
+
+  for line in reader.lines() {
+    let line = match line {
+      Ok(line) => line,
+      Err(_) => return,
+    };
+    if line.is_empty() {
+      continue;
+    }
+
+    let response = match serde_json::from_str::<Request>(&line) {
+      Ok(request) => {
+        let (response_tx, response_rx) = mpsc::channel();
+        if command_tx.send((request, response_tx)).is_err() {
+          Response::Error("Window manager shut down".to_string())
+        } else {
+          response_rx
+            .recv()
+            .unwrap_or_else(|_| Response::Error("Window manager shut down".to_string()))
+        }
+      }
+      Err(err) => Response::Error(format!("Invalid request: {}", err)),
+    };
+
+    let serialized = serde_json::to_string(&response).unwrap_or_else(|_| "null".to_string());
+    if writeln!(writer, "{}", serialized).is_err() {
+      return;
+    }
+  }
+}
+
+// Starts the control socket on its own thread, returning the channel the
+// main loop should poll for incoming commands.
+pub fn start() -> std::io::Result<mpsc::Receiver<IpcCommand>> {
+  let path = socket_path();
+  if let Some(parent) = std::path::Path::new(&path).parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let _ = fs::remove_file(&path);
+
+  let listener = UnixListener::bind(&path)?;
+  // `~/.cache` isn't guaranteed private the way `$XDG_RUNTIME_DIR` is, so
+  // restrict the socket to this user rather than relying on the umask.
+  fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+  let (command_tx, command_rx) = mpsc::channel();
+
+  thread::spawn(move || {
+    for stream in listener.incoming() {
+      if let Ok(stream) = stream {
+        let command_tx = command_tx.clone();
+        thread::spawn(move || handle_connection(stream, command_tx));
+      }
+    }
+  });
+
+  Ok(command_rx)
+}
@@ -1,10 +1,27 @@
+use rust_wm::entities::WindowRule;
 use serde::{Deserialize, Serialize};
 use std::{error::Error, fs};
 use wlral::input::keyboard::KeyboardConfig;
 
+fn default_gaps() -> i32 {
+  0
+}
+
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct Config {
   pub keyboard_layouts: Vec<KeyboardConfig>,
+  #[serde(default)]
+  pub window_rules: Vec<WindowRule>,
+  // Pixel gap inserted between adjacent tiled windows.
+  #[serde(default = "default_gaps")]
+  pub inner_gaps: i32,
+  // Pixel gap inserted between the tiled strip and the monitor edge.
+  #[serde(default = "default_gaps")]
+  pub outer_gaps: i32,
+  // Workspaces pre-created at startup and pinned so they're never garbage
+  // collected, addressable by name regardless of which monitor they end up on.
+  #[serde(default)]
+  pub named_workspaces: Vec<String>,
 }
 
 impl Config {
@@ -27,6 +44,21 @@ impl Config {
       }
     }
 
+    for (i, rule) in config.window_rules.iter().enumerate() {
+      if rule.match_.is_empty() {
+        return Err(format!("Window rule at index {} has no match criteria", i).into());
+      }
+      if !rule.match_.is_checkable() {
+        return Err(
+          format!(
+            "Window rule at index {} only matches on app_id, which isn't supported yet",
+            i
+          )
+          .into(),
+        );
+      }
+    }
+
     Ok(config)
   }
 }